@@ -0,0 +1,700 @@
+//! Stellar Stream: a token-streaming (vesting) contract.
+//!
+//! A stream escrows `total_amount` of a Stellar Asset Contract token in the
+//! contract and releases it to `recipient` between `start_time` and
+//! `end_time`, following the stream's `VestingKind` (linear, linear after a
+//! cliff, or in periodic steps). The sender may cancel a stream at any point.
+
+#![no_std]
+
+#[cfg(test)]
+mod test;
+
+use soroban_sdk::{
+    contract, contracterror, contractimpl, contracttype, symbol_short, token, Address, Env,
+    IntoVal, Symbol, Val, Vec as SorobanVec,
+};
+
+/// The shape of a stream's vesting curve.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum VestingKind {
+    /// Continuous linear release between `start_time` and `end_time`.
+    Linear,
+    /// Nothing vests before `cliff_time`; from then on the vested amount is
+    /// the linear amount measured from `start_time`.
+    CliffThenLinear,
+    /// Vesting jumps in discrete steps every `period_secs`.
+    Monthly { period_secs: u64 },
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Stream {
+    pub sender: Address,
+    pub recipient: Address,
+    pub token: Address,
+    pub total_amount: i128,
+    pub claimed: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub cliff_time: Option<u64>,
+    pub vesting_kind: VestingKind,
+    pub canceled: bool,
+}
+
+#[derive(Clone)]
+#[contracttype]
+enum DataKey {
+    Stream(u64),
+    NextStreamId,
+    Claimer(u64, Address),
+    SenderIndex(Address),
+    RecipientIndex(Address),
+}
+
+/// Lifecycle state of a stream, as surfaced to indexers via `StreamInfo`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum StreamStatus {
+    Active,
+    Canceled,
+}
+
+/// Read-only projection of a stream for off-chain enumeration, returned by
+/// `get_stream`, `streams_by_sender`, and `streams_by_recipient`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamInfo {
+    pub id: u64,
+    pub sender: Address,
+    pub recipient: Address,
+    pub token: Address,
+    pub total_amount: i128,
+    pub claimed: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub cliff_time: Option<u64>,
+    pub vesting_kind: VestingKind,
+    pub status: StreamStatus,
+}
+
+fn to_stream_info(id: u64, stream: &Stream) -> StreamInfo {
+    StreamInfo {
+        id,
+        sender: stream.sender.clone(),
+        recipient: stream.recipient.clone(),
+        token: stream.token.clone(),
+        total_amount: stream.total_amount,
+        claimed: stream.claimed,
+        start_time: stream.start_time,
+        end_time: stream.end_time,
+        cliff_time: stream.cliff_time,
+        vesting_kind: stream.vesting_kind.clone(),
+        status: if stream.canceled {
+            StreamStatus::Canceled
+        } else {
+            StreamStatus::Active
+        },
+    }
+}
+
+#[contracterror]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, PartialOrd, Ord)]
+#[repr(u32)]
+pub enum Error {
+    StreamNotFound = 1,
+    NotRecipient = 2,
+    NotSender = 3,
+    AlreadyCanceled = 4,
+    InvalidTimeRange = 5,
+    InvalidAmount = 6,
+    ClaimExceedsVested = 7,
+    NoAllowance = 8,
+    AllowanceExpired = 9,
+}
+
+const STREAM_CREATED_TOPIC: Symbol = symbol_short!("created");
+const STREAM_CLAIMED_TOPIC: Symbol = symbol_short!("claimed");
+const STREAM_CANCELED_TOPIC: Symbol = symbol_short!("canceled");
+const STREAM_SETTLED_TOPIC: Symbol = symbol_short!("settled");
+const CLAIMER_APPROVED_TOPIC: Symbol = symbol_short!("cl_appr");
+const CLAIMER_REVOKED_TOPIC: Symbol = symbol_short!("cl_revok");
+
+// Event payloads carry only the non-indexed fields; `stream_id` and the
+// relevant party addresses travel in the topic vector instead, so an
+// off-chain indexer can filter by them (e.g. by `recipient`) without
+// decoding every event's data body.
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamCreatedData {
+    pub token: Address,
+    pub total_amount: i128,
+    pub start_time: u64,
+    pub end_time: u64,
+    pub cliff_time: Option<u64>,
+    pub vesting_kind: VestingKind,
+}
+
+fn publish_stream_created(
+    env: &Env,
+    stream_id: u64,
+    sender: Address,
+    recipient: Address,
+    data: StreamCreatedData,
+) {
+    env.events()
+        .publish((STREAM_CREATED_TOPIC, stream_id, sender, recipient), data);
+}
+
+#[cfg(test)]
+fn stream_created_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    sender: Address,
+    recipient: Address,
+    data: StreamCreatedData,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [
+                STREAM_CREATED_TOPIC.into_val(env),
+                stream_id.into_val(env),
+                sender.into_val(env),
+                recipient.into_val(env),
+            ],
+        ),
+        data.into_val(env),
+    )
+}
+
+fn publish_stream_claimed(env: &Env, stream_id: u64, recipient: Address, amount: i128) {
+    env.events()
+        .publish((STREAM_CLAIMED_TOPIC, recipient, stream_id), amount);
+}
+
+#[cfg(test)]
+fn stream_claimed_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    recipient: Address,
+    amount: i128,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [
+                STREAM_CLAIMED_TOPIC.into_val(env),
+                recipient.into_val(env),
+                stream_id.into_val(env),
+            ],
+        ),
+        amount.into_val(env),
+    )
+}
+
+fn publish_stream_canceled(env: &Env, stream_id: u64, sender: Address) {
+    env.events()
+        .publish((STREAM_CANCELED_TOPIC, sender, stream_id), ());
+}
+
+#[cfg(test)]
+fn stream_canceled_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    sender: Address,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [
+                STREAM_CANCELED_TOPIC.into_val(env),
+                sender.into_val(env),
+                stream_id.into_val(env),
+            ],
+        ),
+        ().into_val(env),
+    )
+}
+
+/// Emitted alongside `StreamCanceled` with the pro-rata split of escrowed
+/// funds between the recipient (vested-but-unclaimed) and the sender
+/// (unvested remainder).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StreamSettledData {
+    pub recipient_amount: i128,
+    pub sender_refund: i128,
+}
+
+fn publish_stream_settled(env: &Env, stream_id: u64, data: StreamSettledData) {
+    env.events().publish((STREAM_SETTLED_TOPIC, stream_id), data);
+}
+
+#[cfg(test)]
+fn stream_settled_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    data: StreamSettledData,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [STREAM_SETTLED_TOPIC.into_val(env), stream_id.into_val(env)],
+        ),
+        data.into_val(env),
+    )
+}
+
+fn publish_claimer_approved(
+    env: &Env,
+    stream_id: u64,
+    recipient: Address,
+    operator: Address,
+    expiration: u64,
+) {
+    env.events().publish(
+        (CLAIMER_APPROVED_TOPIC, stream_id, recipient, operator),
+        expiration,
+    );
+}
+
+#[cfg(test)]
+fn claimer_approved_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    recipient: Address,
+    operator: Address,
+    expiration: u64,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [
+                CLAIMER_APPROVED_TOPIC.into_val(env),
+                stream_id.into_val(env),
+                recipient.into_val(env),
+                operator.into_val(env),
+            ],
+        ),
+        expiration.into_val(env),
+    )
+}
+
+fn publish_claimer_revoked(env: &Env, stream_id: u64, recipient: Address, operator: Address) {
+    env.events().publish(
+        (CLAIMER_REVOKED_TOPIC, stream_id, recipient, operator),
+        (),
+    );
+}
+
+#[cfg(test)]
+fn claimer_revoked_xdr(
+    env: &Env,
+    contract_id: &Address,
+    stream_id: u64,
+    recipient: Address,
+    operator: Address,
+) -> (Address, SorobanVec<Val>, Val) {
+    (
+        contract_id.clone(),
+        SorobanVec::from_array(
+            env,
+            [
+                CLAIMER_REVOKED_TOPIC.into_val(env),
+                stream_id.into_val(env),
+                recipient.into_val(env),
+                operator.into_val(env),
+            ],
+        ),
+        ().into_val(env),
+    )
+}
+
+fn read_stream(env: &Env, stream_id: u64) -> Result<Stream, Error> {
+    env.storage()
+        .persistent()
+        .get(&DataKey::Stream(stream_id))
+        .ok_or(Error::StreamNotFound)
+}
+
+fn write_stream(env: &Env, stream_id: u64, stream: &Stream) {
+    env.storage()
+        .persistent()
+        .set(&DataKey::Stream(stream_id), stream);
+}
+
+fn append_index(env: &Env, key: DataKey, stream_id: u64) {
+    let mut ids: SorobanVec<u64> = env
+        .storage()
+        .persistent()
+        .get(&key)
+        .unwrap_or_else(|| SorobanVec::new(env));
+    ids.push_back(stream_id);
+    env.storage().persistent().set(&key, &ids);
+}
+
+/// Page through `ids` (in ascending creation order), returning up to `limit`
+/// entries strictly after `start_after`.
+fn paginate_streams(
+    env: &Env,
+    ids: SorobanVec<u64>,
+    start_after: Option<u64>,
+    limit: u32,
+) -> SorobanVec<StreamInfo> {
+    let mut page = SorobanVec::new(env);
+    let mut skipping = start_after.is_some();
+    for id in ids.iter() {
+        if skipping {
+            if Some(id) == start_after {
+                skipping = false;
+            }
+            continue;
+        }
+        if page.len() >= limit {
+            break;
+        }
+        if let Ok(stream) = read_stream(env, id) {
+            page.push_back(to_stream_info(id, &stream));
+        }
+    }
+    page
+}
+
+/// Amount vested (released from escrow) for `stream` at ledger time `now`,
+/// according to the stream's `vesting_kind`: zero up to `start_time`,
+/// `total_amount` from `end_time` on.
+fn vested_amount(stream: &Stream, now: u64) -> i128 {
+    if now <= stream.start_time {
+        return 0;
+    }
+    if now >= stream.end_time {
+        return stream.total_amount;
+    }
+
+    match &stream.vesting_kind {
+        VestingKind::Linear => linear_vested(stream, now),
+        VestingKind::CliffThenLinear => {
+            let cliff = stream.cliff_time.unwrap_or(stream.start_time);
+            if now < cliff {
+                0
+            } else {
+                linear_vested(stream, now)
+            }
+        }
+        VestingKind::Monthly { period_secs } => {
+            if *period_secs == 0 {
+                return linear_vested(stream, now);
+            }
+            let duration = stream.end_time - stream.start_time;
+            let periods_elapsed = (now - stream.start_time) / period_secs;
+            let step_amount = stream.total_amount * (*period_secs as i128) / (duration as i128);
+            (step_amount * periods_elapsed as i128).min(stream.total_amount)
+        }
+    }
+}
+
+fn linear_vested(stream: &Stream, now: u64) -> i128 {
+    let elapsed = (now - stream.start_time) as i128;
+    let duration = (stream.end_time - stream.start_time) as i128;
+    stream.total_amount * elapsed / duration
+}
+
+fn do_claim(env: &Env, stream_id: u64, recipient: Address, amount: i128) -> Result<i128, Error> {
+    let mut stream = read_stream(env, stream_id)?;
+    if stream.recipient != recipient {
+        return Err(Error::NotRecipient);
+    }
+    if stream.canceled {
+        return Err(Error::AlreadyCanceled);
+    }
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let now = env.ledger().timestamp();
+    let claimable = vested_amount(&stream, now) - stream.claimed;
+    if amount > claimable {
+        return Err(Error::ClaimExceedsVested);
+    }
+
+    stream.claimed += amount;
+    write_stream(env, stream_id, &stream);
+
+    token::Client::new(env, &stream.token).transfer(&env.current_contract_address(), &recipient, &amount);
+
+    publish_stream_claimed(env, stream_id, recipient, amount);
+
+    Ok(amount)
+}
+
+#[contract]
+pub struct StellarStreamContract;
+
+#[contractimpl]
+impl StellarStreamContract {
+    /// Create a new stream, pulling `total_amount` of `token` from `sender`
+    /// into the contract to be released to `recipient` between `start_time`
+    /// and `end_time`.
+    pub fn create_stream(
+        env: Env,
+        sender: Address,
+        recipient: Address,
+        token: Address,
+        total_amount: i128,
+        start_time: u64,
+        end_time: u64,
+        cliff_time: Option<u64>,
+        vesting_kind: VestingKind,
+    ) -> Result<u64, Error> {
+        sender.require_auth();
+
+        if total_amount <= 0 {
+            return Err(Error::InvalidAmount);
+        }
+        if start_time >= end_time {
+            return Err(Error::InvalidTimeRange);
+        }
+        match cliff_time {
+            Some(cliff) if start_time <= cliff && cliff <= end_time => {}
+            Some(_) => return Err(Error::InvalidTimeRange),
+            None if matches!(vesting_kind, VestingKind::CliffThenLinear) => {
+                return Err(Error::InvalidTimeRange)
+            }
+            None => {}
+        }
+
+        let stream_id = env
+            .storage()
+            .instance()
+            .get(&DataKey::NextStreamId)
+            .unwrap_or(0u64);
+        env.storage()
+            .instance()
+            .set(&DataKey::NextStreamId, &(stream_id + 1));
+
+        token::Client::new(&env, &token).transfer(
+            &sender,
+            &env.current_contract_address(),
+            &total_amount,
+        );
+
+        let stream = Stream {
+            sender: sender.clone(),
+            recipient: recipient.clone(),
+            token: token.clone(),
+            total_amount,
+            claimed: 0,
+            start_time,
+            end_time,
+            cliff_time,
+            vesting_kind: vesting_kind.clone(),
+            canceled: false,
+        };
+        write_stream(&env, stream_id, &stream);
+        append_index(&env, DataKey::SenderIndex(sender.clone()), stream_id);
+        append_index(&env, DataKey::RecipientIndex(recipient.clone()), stream_id);
+
+        publish_stream_created(
+            &env,
+            stream_id,
+            sender,
+            recipient,
+            StreamCreatedData {
+                token,
+                total_amount,
+                start_time,
+                end_time,
+                cliff_time,
+                vesting_kind,
+            },
+        );
+
+        Ok(stream_id)
+    }
+
+    /// Fetch a single stream by id for off-chain enumeration.
+    pub fn get_stream(env: Env, stream_id: u64) -> Result<StreamInfo, Error> {
+        read_stream(&env, stream_id).map(|stream| to_stream_info(stream_id, &stream))
+    }
+
+    /// Total number of streams ever created.
+    pub fn stream_count(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::NextStreamId)
+            .unwrap_or(0)
+    }
+
+    /// Paginate the streams sent by `sender`, oldest first. `start_after`
+    /// (when set) is the last `stream_id` seen in the previous page.
+    pub fn streams_by_sender(
+        env: Env,
+        sender: Address,
+        start_after: Option<u64>,
+        limit: u32,
+    ) -> SorobanVec<StreamInfo> {
+        let ids = env
+            .storage()
+            .persistent()
+            .get(&DataKey::SenderIndex(sender))
+            .unwrap_or_else(|| SorobanVec::new(&env));
+        paginate_streams(&env, ids, start_after, limit)
+    }
+
+    /// Paginate the streams received by `recipient`, oldest first.
+    /// `start_after` (when set) is the last `stream_id` seen in the previous
+    /// page.
+    pub fn streams_by_recipient(
+        env: Env,
+        recipient: Address,
+        start_after: Option<u64>,
+        limit: u32,
+    ) -> SorobanVec<StreamInfo> {
+        let ids = env
+            .storage()
+            .persistent()
+            .get(&DataKey::RecipientIndex(recipient))
+            .unwrap_or_else(|| SorobanVec::new(&env));
+        paginate_streams(&env, ids, start_after, limit)
+    }
+
+    /// Claim exactly `amount` of the vested-but-unclaimed balance of a stream,
+    /// pushing it from the contract's escrow to `recipient`. Fails with
+    /// `ClaimExceedsVested` if `amount` is more than that balance.
+    pub fn claim(env: Env, stream_id: u64, recipient: Address, amount: i128) -> Result<i128, Error> {
+        recipient.require_auth();
+        do_claim(&env, stream_id, recipient, amount)
+    }
+
+    /// Claim on behalf of `recipient` as a pre-approved `operator` (see
+    /// `approve_claimer`). Proceeds still go to `recipient`.
+    pub fn claim_from(
+        env: Env,
+        stream_id: u64,
+        operator: Address,
+        recipient: Address,
+        amount: i128,
+    ) -> Result<i128, Error> {
+        operator.require_auth();
+
+        let expiration: u64 = env
+            .storage()
+            .persistent()
+            .get(&DataKey::Claimer(stream_id, operator))
+            .ok_or(Error::NoAllowance)?;
+        if env.ledger().timestamp() >= expiration {
+            return Err(Error::AllowanceExpired);
+        }
+
+        do_claim(&env, stream_id, recipient, amount)
+    }
+
+    /// Authorize `operator` to call `claim_from` for this stream's proceeds
+    /// until `expiration` (a ledger timestamp).
+    pub fn approve_claimer(
+        env: Env,
+        stream_id: u64,
+        recipient: Address,
+        operator: Address,
+        expiration: u64,
+    ) -> Result<(), Error> {
+        recipient.require_auth();
+
+        let stream = read_stream(&env, stream_id)?;
+        if stream.recipient != recipient {
+            return Err(Error::NotRecipient);
+        }
+
+        env.storage()
+            .persistent()
+            .set(&DataKey::Claimer(stream_id, operator.clone()), &expiration);
+
+        publish_claimer_approved(&env, stream_id, recipient, operator, expiration);
+
+        Ok(())
+    }
+
+    /// Revoke a previously-granted `approve_claimer` delegation.
+    pub fn revoke_claimer(
+        env: Env,
+        stream_id: u64,
+        recipient: Address,
+        operator: Address,
+    ) -> Result<(), Error> {
+        recipient.require_auth();
+
+        let stream = read_stream(&env, stream_id)?;
+        if stream.recipient != recipient {
+            return Err(Error::NotRecipient);
+        }
+
+        env.storage()
+            .persistent()
+            .remove(&DataKey::Claimer(stream_id, operator.clone()));
+
+        publish_claimer_revoked(&env, stream_id, recipient, operator);
+
+        Ok(())
+    }
+
+    /// Cancel a stream, settling it pro-rata: the recipient receives
+    /// whatever has vested but was not yet claimed, and the sender is
+    /// refunded the unvested remainder. Canceling an already-canceled
+    /// stream is a no-op.
+    pub fn cancel(env: Env, stream_id: u64, sender: Address) -> Result<(), Error> {
+        sender.require_auth();
+
+        let mut stream = read_stream(&env, stream_id)?;
+        if stream.sender != sender {
+            return Err(Error::NotSender);
+        }
+        if stream.canceled {
+            return Ok(());
+        }
+
+        let now = env.ledger().timestamp();
+        let vested = vested_amount(&stream, now);
+        let recipient_amount = vested - stream.claimed;
+        let sender_refund = stream.total_amount - vested;
+
+        stream.canceled = true;
+        write_stream(&env, stream_id, &stream);
+
+        let token_client = token::Client::new(&env, &stream.token);
+        if recipient_amount > 0 {
+            token_client.transfer(
+                &env.current_contract_address(),
+                &stream.recipient,
+                &recipient_amount,
+            );
+        }
+        if sender_refund > 0 {
+            token_client.transfer(&env.current_contract_address(), &sender, &sender_refund);
+        }
+
+        publish_stream_canceled(&env, stream_id, sender);
+        publish_stream_settled(
+            &env,
+            stream_id,
+            StreamSettledData {
+                recipient_amount,
+                sender_refund,
+            },
+        );
+
+        Ok(())
+    }
+}
@@ -5,6 +5,18 @@ extern crate std;
 use super::*;
 use soroban_sdk::{testutils::Events, Address, Env};
 
+fn create_token_contract<'a>(
+    env: &Env,
+    admin: &Address,
+) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
+    let sac = env.register_stellar_asset_contract_v2(admin.clone());
+    let address = sac.address();
+    (
+        token::Client::new(env, &address),
+        token::StellarAssetClient::new(env, &address),
+    )
+}
+
 #[test]
 fn test_create_stream_emits_event() {
     let env = Env::default();
@@ -15,11 +27,15 @@ fn test_create_stream_emits_event() {
 
     let sender = Address::generate(&env);
     let recipient = Address::generate(&env);
-    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
     let total_amount: i128 = 1000;
     let start_time: u64 = 1000;
     let end_time: u64 = 2000;
 
+    token_sac.mint(&sender, &total_amount);
+
     let stream_id = client.create_stream(
         &sender,
         &recipient,
@@ -27,21 +43,32 @@ fn test_create_stream_emits_event() {
         &total_amount,
         &start_time,
         &end_time,
+        &None,
+        &VestingKind::Linear,
     );
 
-    // Verify StreamCreated event was emitted
+    // Funds were pulled into escrow.
+    assert_eq!(token_client.balance(&sender), 0);
+    assert_eq!(token_client.balance(&contract_id), total_amount);
+
+    // Verify StreamCreated was emitted with the indexable fields as topics.
     assert_eq!(
         env.events().all(),
-        std::vec![StreamCreated {
+        std::vec![stream_created_xdr(
+            &env,
+            &contract_id,
             stream_id,
-            sender: sender.clone(),
-            recipient: recipient.clone(),
-            token: token.clone(),
-            total_amount,
-            start_time,
-            end_time,
-        }
-        .to_xdr(&env, &contract_id)]
+            sender.clone(),
+            recipient.clone(),
+            StreamCreatedData {
+                token: token.clone(),
+                total_amount,
+                start_time,
+                end_time,
+                cliff_time: None,
+                vesting_kind: VestingKind::Linear,
+            },
+        )]
     );
 }
 
@@ -55,11 +82,15 @@ fn test_claim_emits_event() {
 
     let sender = Address::generate(&env);
     let recipient = Address::generate(&env);
-    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
     let total_amount: i128 = 1000;
     let start_time: u64 = 1000;
     let end_time: u64 = 2000;
 
+    token_sac.mint(&sender, &total_amount);
+
     // Create a stream first
     let stream_id = client.create_stream(
         &sender,
@@ -68,6 +99,8 @@ fn test_claim_emits_event() {
         &total_amount,
         &start_time,
         &end_time,
+        &None,
+        &VestingKind::Linear,
     );
 
     // Clear events from create_stream
@@ -80,19 +113,61 @@ fn test_claim_emits_event() {
     let claimed = client.claim(&stream_id, &recipient, &claim_amount);
 
     assert_eq!(claimed, claim_amount);
+    assert_eq!(token_client.balance(&recipient), claim_amount);
+    assert_eq!(
+        token_client.balance(&contract_id),
+        total_amount - claim_amount
+    );
 
-    // Verify StreamClaimed event was emitted
+    // Verify StreamClaimed was emitted with recipient/stream_id as topics.
     assert_eq!(
         env.events().all(),
-        std::vec![StreamClaimed {
+        std::vec![stream_claimed_xdr(
+            &env,
+            &contract_id,
             stream_id,
-            recipient: recipient.clone(),
-            amount: claim_amount,
-        }
-        .to_xdr(&env, &contract_id)]
+            recipient.clone(),
+            claim_amount,
+        )]
     );
 }
 
+#[test]
+fn test_claim_above_vested_amount_fails() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Linear,
+    );
+
+    env.ledger().set_timestamp(start_time + 500);
+
+    let result = client.try_claim(&stream_id, &recipient, &600);
+    assert_eq!(result, Err(Ok(Error::ClaimExceedsVested)));
+}
+
 #[test]
 fn test_cancel_emits_event() {
     let env = Env::default();
@@ -103,11 +178,15 @@ fn test_cancel_emits_event() {
 
     let sender = Address::generate(&env);
     let recipient = Address::generate(&env);
-    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
     let total_amount: i128 = 1000;
     let start_time: u64 = 1000;
     let end_time: u64 = 2000;
 
+    token_sac.mint(&sender, &total_amount);
+
     // Create a stream first
     let stream_id = client.create_stream(
         &sender,
@@ -116,6 +195,8 @@ fn test_cancel_emits_event() {
         &total_amount,
         &start_time,
         &end_time,
+        &None,
+        &VestingKind::Linear,
     );
 
     // Clear events from create_stream
@@ -124,15 +205,266 @@ fn test_cancel_emits_event() {
     // Cancel the stream
     client.cancel(&stream_id, &sender);
 
-    // Verify StreamCanceled event was emitted
+    // The full, unclaimed escrow is refunded to the sender.
+    assert_eq!(token_client.balance(&sender), total_amount);
+    assert_eq!(token_client.balance(&contract_id), 0);
+
+    // Verify StreamCanceled and StreamSettled were emitted with sender/stream_id as topics.
     assert_eq!(
         env.events().all(),
-        std::vec![StreamCanceled {
+        std::vec![
+            stream_canceled_xdr(&env, &contract_id, stream_id, sender.clone()),
+            stream_settled_xdr(
+                &env,
+                &contract_id,
+                stream_id,
+                StreamSettledData {
+                    recipient_amount: 0,
+                    sender_refund: total_amount,
+                },
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_cancel_mid_stream_splits_pro_rata() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Linear,
+    );
+
+    // Halfway through the stream: 500 vested, none claimed yet.
+    env.ledger().set_timestamp(start_time + 500);
+    env.events().clear();
+
+    client.cancel(&stream_id, &sender);
+
+    assert_eq!(token_client.balance(&recipient), 500);
+    assert_eq!(token_client.balance(&sender), 500);
+    assert_eq!(token_client.balance(&contract_id), 0);
+
+    assert_eq!(
+        env.events().all(),
+        std::vec![
+            stream_canceled_xdr(&env, &contract_id, stream_id, sender.clone()),
+            stream_settled_xdr(
+                &env,
+                &contract_id,
+                stream_id,
+                StreamSettledData {
+                    recipient_amount: 500,
+                    sender_refund: 500,
+                },
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_claim_from_approved_operator() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Linear,
+    );
+
+    env.events().clear();
+    client.approve_claimer(&stream_id, &recipient, &operator, &(end_time + 1));
+
+    assert_eq!(
+        env.events().all(),
+        std::vec![claimer_approved_xdr(
+            &env,
+            &contract_id,
             stream_id,
-            sender: sender.clone(),
-        }
-        .to_xdr(&env, &contract_id)]
+            recipient.clone(),
+            operator.clone(),
+            end_time + 1,
+        )]
+    );
+
+    env.ledger().set_timestamp(start_time + 500);
+
+    let claimed = client.claim_from(&stream_id, &operator, &recipient, &500);
+    assert_eq!(claimed, 500);
+    assert_eq!(token_client.balance(&recipient), 500);
+}
+
+#[test]
+fn test_claim_from_fails_after_expiration() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Linear,
     );
+
+    client.approve_claimer(&stream_id, &recipient, &operator, &(start_time + 100));
+
+    env.ledger().set_timestamp(start_time + 500);
+
+    let result = client.try_claim_from(&stream_id, &operator, &recipient, &500);
+    assert_eq!(result, Err(Ok(Error::AllowanceExpired)));
+}
+
+#[test]
+fn test_claim_from_fails_after_revocation() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let operator = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Linear,
+    );
+
+    client.approve_claimer(&stream_id, &recipient, &operator, &(end_time + 1));
+    env.events().clear();
+    client.revoke_claimer(&stream_id, &recipient, &operator);
+
+    assert_eq!(
+        env.events().all(),
+        std::vec![claimer_revoked_xdr(
+            &env,
+            &contract_id,
+            stream_id,
+            recipient.clone(),
+            operator.clone(),
+        )]
+    );
+
+    env.ledger().set_timestamp(start_time + 500);
+
+    let result = client.try_claim_from(&stream_id, &operator, &recipient, &500);
+    assert_eq!(result, Err(Ok(Error::NoAllowance)));
+}
+
+#[test]
+fn test_registry_enumeration_and_pagination() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    token_sac.mint(&sender, &3000);
+
+    let id0 = client.create_stream(&sender, &recipient, &token, &1000, &1000, &2000, &None, &VestingKind::Linear);
+    let id1 = client.create_stream(&sender, &recipient, &token, &1000, &1000, &2000, &None, &VestingKind::Linear);
+    let id2 = client.create_stream(&sender, &recipient, &token, &1000, &1000, &2000, &None, &VestingKind::Linear);
+
+    assert_eq!(client.stream_count(), 3);
+
+    let info = client.get_stream(&id1);
+    assert_eq!(info.id, id1);
+    assert_eq!(info.sender, sender);
+    assert_eq!(info.status, StreamStatus::Active);
+
+    let first_page = client.streams_by_sender(&sender, &None, &2);
+    assert_eq!(first_page.len(), 2);
+    assert_eq!(first_page.get(0).unwrap().id, id0);
+    assert_eq!(first_page.get(1).unwrap().id, id1);
+
+    let second_page = client.streams_by_sender(&sender, &Some(id1), &2);
+    assert_eq!(second_page.len(), 1);
+    assert_eq!(second_page.get(0).unwrap().id, id2);
+
+    let recipient_streams = client.streams_by_recipient(&recipient, &None, &10);
+    assert_eq!(recipient_streams.len(), 3);
 }
 
 #[test]
@@ -145,11 +477,15 @@ fn test_cancel_does_not_emit_event_when_already_canceled() {
 
     let sender = Address::generate(&env);
     let recipient = Address::generate(&env);
-    let token = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
     let total_amount: i128 = 1000;
     let start_time: u64 = 1000;
     let end_time: u64 = 2000;
 
+    token_sac.mint(&sender, &total_amount);
+
     // Create a stream first
     let stream_id = client.create_stream(
         &sender,
@@ -158,6 +494,8 @@ fn test_cancel_does_not_emit_event_when_already_canceled() {
         &total_amount,
         &start_time,
         &end_time,
+        &None,
+        &VestingKind::Linear,
     );
 
     // Clear events from create_stream
@@ -175,3 +513,179 @@ fn test_cancel_does_not_emit_event_when_already_canceled() {
     // Verify no new event was emitted
     assert_eq!(env.events().all(), std::vec![]);
 }
+
+#[test]
+fn test_cliff_then_linear_vests_nothing_before_cliff() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1000;
+    let start_time: u64 = 1000;
+    let cliff_time: u64 = 1500;
+    let end_time: u64 = 2000;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &Some(cliff_time),
+        &VestingKind::CliffThenLinear,
+    );
+
+    // Before the cliff, nothing is claimable even though the stream started.
+    env.ledger().set_timestamp(start_time + 100);
+    let result = client.try_claim(&stream_id, &recipient, &1);
+    assert_eq!(result, Err(Ok(Error::ClaimExceedsVested)));
+
+    // Past the cliff, the linear formula applies from start_time as usual.
+    env.ledger().set_timestamp(cliff_time + 250);
+    let claimed = client.claim(&stream_id, &recipient, &750);
+    assert_eq!(claimed, 750);
+}
+
+#[test]
+fn test_create_stream_rejects_cliff_outside_stream_range() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+
+    token_sac.mint(&sender, &1000);
+
+    let result = client.try_create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &1000,
+        &2000,
+        &Some(2500),
+        &VestingKind::CliffThenLinear,
+    );
+    assert_eq!(result, Err(Ok(Error::InvalidTimeRange)));
+}
+
+#[test]
+fn test_monthly_vesting_releases_in_steps() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    let total_amount: i128 = 1200;
+    let start_time: u64 = 0;
+    let end_time: u64 = 1200;
+    let period_secs: u64 = 300;
+
+    token_sac.mint(&sender, &total_amount);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &total_amount,
+        &start_time,
+        &end_time,
+        &None,
+        &VestingKind::Monthly { period_secs },
+    );
+
+    // One period elapsed: a single 300/1200 share (300 tokens) has vested.
+    env.ledger().set_timestamp(period_secs);
+    let claimed = client.claim(&stream_id, &recipient, &300);
+    assert_eq!(claimed, 300);
+
+    // Still within the second period: nothing new has vested yet.
+    env.ledger().set_timestamp(period_secs + 100);
+    let result = client.try_claim(&stream_id, &recipient, &1);
+    assert_eq!(result, Err(Ok(Error::ClaimExceedsVested)));
+
+    // Two periods elapsed: another 300 share becomes claimable.
+    env.ledger().set_timestamp(period_secs * 2);
+    let claimed = client.claim(&stream_id, &recipient, &300);
+    assert_eq!(claimed, 300);
+}
+
+#[test]
+fn test_events_are_filterable_by_recipient_topic() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register_contract(None, StellarStreamContract);
+    let client = StellarStreamContractClient::new(&env, &contract_id);
+
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+    let other_recipient = Address::generate(&env);
+    let admin = Address::generate(&env);
+    let (token_client, token_sac) = create_token_contract(&env, &admin);
+    let token = token_client.address.clone();
+    token_sac.mint(&sender, &2000);
+
+    let stream_id = client.create_stream(
+        &sender,
+        &recipient,
+        &token,
+        &1000,
+        &1000,
+        &2000,
+        &None,
+        &VestingKind::Linear,
+    );
+    client.create_stream(
+        &sender,
+        &other_recipient,
+        &token,
+        &1000,
+        &1000,
+        &2000,
+        &None,
+        &VestingKind::Linear,
+    );
+
+    env.ledger().set_timestamp(1500);
+    client.claim(&stream_id, &recipient, &500);
+
+    // An indexer watching only `recipient` can find its event by scanning
+    // topic[1] without decoding any event whose topic[1] is a different
+    // address.
+    let recipient_topic: Val = recipient.clone().into_val(&env);
+    let matches: std::vec::Vec<_> = env
+        .events()
+        .all()
+        .iter()
+        .filter(|entry| entry.1.get(1) == Some(recipient_topic))
+        .cloned()
+        .collect();
+    assert_eq!(matches.len(), 1);
+    assert_eq!(
+        matches[0],
+        stream_claimed_xdr(&env, &contract_id, stream_id, recipient, 500)
+    );
+}